@@ -0,0 +1,117 @@
+use hyper::header;
+use std::collections::HashSet;
+use std::fs;
+use std::io::BufRead;
+use std::io;
+
+// Validates bearer/basic credentials on mutating requests, or trusts an
+// identity header asserted by an upstream authenticating reverse proxy.
+pub struct AuthConfig {
+    tokens: HashSet<String>,
+    trust_proxy_user_header: Option<String>
+}
+
+impl AuthConfig {
+
+    pub fn new(tokens: HashSet<String>, trust_proxy_user_header: Option<String>) -> AuthConfig {
+        AuthConfig{tokens, trust_proxy_user_header}
+    }
+
+    pub fn authorize(&self, headers: &header::Headers) -> bool {
+        if self.tokens.is_empty() && self.trust_proxy_user_header.is_none() {
+            return true;
+        }
+
+        if let Some(ref header_name) = self.trust_proxy_user_header {
+            return headers.get_raw(header_name)
+                .and_then(|raw| raw.first())
+                .map(|value| !value.is_empty())
+                .unwrap_or(false);
+        }
+
+        if let Some(auth) = headers.get::<header::Authorization<header::Bearer>>() {
+            return self.token_matches(&auth.token);
+        }
+
+        if let Some(auth) = headers.get::<header::Authorization<header::Basic>>() {
+            return auth.password.as_ref().map(|pw| self.token_matches(pw)).unwrap_or(false);
+        }
+
+        false
+    }
+
+    fn token_matches(&self, candidate: &str) -> bool {
+        self.tokens.iter().any(|token| tokens_equal(token, candidate))
+    }
+
+    // Pulls the raw bearer token or basic-auth password out of the request,
+    // without checking it against the globally accepted set.
+    pub fn extract_token(&self, headers: &header::Headers) -> Option<String> {
+        if let Some(auth) = headers.get::<header::Authorization<header::Bearer>>() {
+            return Some(auth.token.clone());
+        }
+
+        if let Some(auth) = headers.get::<header::Authorization<header::Basic>>() {
+            return auth.password.clone();
+        }
+
+        None
+    }
+}
+
+pub fn token_allowed(candidate: &str, allowed: &[String]) -> bool {
+    allowed.iter().any(|token| tokens_equal(token, candidate))
+}
+
+// Constant-time comparison for equal-length inputs, so token checks don't leak
+// a matching prefix via timing; a length mismatch still returns early.
+fn tokens_equal(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut diff = 0u8;
+    for (x, y) in a.bytes().zip(b.bytes()) {
+        diff |= x ^ y;
+    }
+
+    diff == 0
+}
+
+pub fn load_tokens_file(path: &str) -> HashSet<String> {
+    let file = fs::File::open(path).unwrap_or_else(|error| panic!("Failed to open tokens file {}: {}", path, error));
+    let reader = io::BufReader::new(file);
+
+    reader.lines()
+        .map_while(Result::ok)
+        .map(|line| line.trim().to_owned())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_equal_matches_identical_strings() {
+        assert!(tokens_equal("s3cr3t", "s3cr3t"));
+    }
+
+    #[test]
+    fn tokens_equal_rejects_different_strings() {
+        assert!(!tokens_equal("s3cr3t", "wrong"));
+    }
+
+    #[test]
+    fn tokens_equal_rejects_different_lengths() {
+        assert!(!tokens_equal("short", "longer-token"));
+    }
+
+    #[test]
+    fn token_allowed_checks_the_whole_list() {
+        let allowed = vec!["a".to_owned(), "b".to_owned()];
+        assert!(token_allowed("b", &allowed));
+        assert!(!token_allowed("c", &allowed));
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RepoConfig {
+    pub path: String,
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    #[serde(default)]
+    pub signing_key: Option<String>,
+    #[serde(default)]
+    pub signing_passphrase: Option<String>,
+    #[serde(default)]
+    pub retain: Option<usize>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Config {
+    pub repos: HashMap<String, RepoConfig>
+}
+
+impl Config {
+    pub fn repo(&self, repo_name: &str) -> Option<&RepoConfig> {
+        self.repos.get(repo_name)
+    }
+}
+
+// Loads a TOML or YAML document describing the repositories this server is
+// allowed to serve, picking the format from the file extension.
+pub fn load(path: &str) -> Config {
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|error| panic!("Failed to read config file {}: {}", path, error));
+
+    if path.ends_with(".yaml") || path.ends_with(".yml") {
+        serde_yaml::from_str(&contents)
+            .unwrap_or_else(|error| panic!("Failed to parse YAML config {}: {}", path, error))
+    } else {
+        toml::from_str(&contents)
+            .unwrap_or_else(|error| panic!("Failed to parse TOML config {}: {}", path, error))
+    }
+}
+
+// Resolves where a repo's files live on disk: its configured `path` when a
+// config declares it, otherwise the legacy `<file_root>/<repo_name>` layout.
+pub fn repo_dir(file_root: &str, config: Option<&Config>, repo_name: &str) -> path::PathBuf {
+    match config.and_then(|config| config.repo(repo_name)) {
+        Some(repo_config) => path::PathBuf::from(&repo_config.path),
+        None => path::Path::new(file_root).join(repo_name)
+    }
+}
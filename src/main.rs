@@ -3,16 +3,45 @@ extern crate hyper;
 extern crate log;
 extern crate env_logger;
 extern crate clap;
-
+extern crate sha2;
+extern crate hex;
+extern crate base64;
+extern crate hyper_native_tls;
+extern crate native_tls;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_yaml;
+extern crate toml;
+
+mod auth;
+mod config;
+mod retention;
+mod signing;
+mod worker;
+
+use auth::AuthConfig;
+use config::Config;
+use hyper::header;
 use hyper::method;
 use hyper::server;
 use hyper::status;
+use hyper_native_tls::NativeTlsServer;
+use native_tls::{Identity, TlsAcceptor};
+use sha2::Digest;
+use sha2::Sha256;
+use std::collections::HashSet;
 use std::fs;
 use std::io;
+use std::io::Read;
+use std::io::Write;
 use std::path;
-use std::process;
 use std::string;
-use std::sync;
+use std::sync::Arc;
+use worker::JobStatus;
+use worker::RebuildWorker;
+
+const REBUILD_WORKER_POOL_SIZE: usize = 2;
 
 #[derive(Debug)]
 pub struct HttpError {
@@ -20,10 +49,19 @@ pub struct HttpError {
     error: String
 }
 
+impl HttpError {
+    // Logs the reason a request was rejected and returns the status code to
+    // send back, so every call site doesn't have to remember to log it.
+    fn into_status(self) -> status::StatusCode {
+        debug!("Rejecting request: {}", self.error);
+        self.code
+    }
+}
+
 #[derive(Debug)]
 pub struct RepoRequest {
     repo_name: String,
-    file_name: Option<String>
+    path_parts: Vec<String>
 }
 
 impl RepoRequest {
@@ -40,39 +78,48 @@ impl RepoRequest {
         }
     }
 
-    fn ensure_repo_exists(&self, root: &String) {
-        let repo_path = path::Path::new(root).join(path::Path::new(&self.repo_name));
-        self.ensure_dir_exists(&repo_path);
-        let rpm_path = repo_path.as_path().join(path::Path::new("rpms"));
+    fn ensure_repo_exists(&self, repo_dir: &path::Path) {
+        self.ensure_dir_exists(repo_dir);
+        let rpm_path = repo_dir.join(path::Path::new("rpms"));
         self.ensure_dir_exists(rpm_path.as_path());
     }
 
-    fn repo_path(&self, root: &String) -> String {
-        let repo_path = path::Path::new(root).join(path::Path::new(&self.repo_name));
-        repo_path.to_str().unwrap().to_owned()
-    }
+    // Resolves the on-disk path for a single-component `/<repo>/<name>.rpm`
+    // request, rejecting a missing or non-`.rpm` name with a client error
+    // instead of panicking the handler thread on malformed input.
+    fn file_path(&self, repo_dir: &path::Path) -> Result<String, HttpError> {
+        let name = match self.path_parts.first() {
+            Some(name) => name,
+            None => return Err(HttpError{code: status::StatusCode::BadRequest, error: "Missing package file name".to_owned()})
+        };
 
-    fn file_path(&self, root: &String) -> String {
-        let repo_path = self.repo_path(root);
-        let rpm_path = path::Path::new(&repo_path).join(path::Path::new("rpms"));
-        let name = self.file_name.to_owned().unwrap();
-        let file_path = rpm_path.as_path().join(path::Path::new(&name));
+        let rpm_path = repo_dir.join(path::Path::new("rpms"));
+        let file_path = rpm_path.as_path().join(path::Path::new(name));
 
-        let extension = file_path.extension().unwrap().to_str().unwrap().to_owned();
+        let extension = file_path.extension().and_then(|ext| ext.to_str());
 
-        if extension != "rpm" {
-            panic!("Unexpected file name {}, it must be rpm file", name);
+        if extension != Some("rpm") {
+            return Err(HttpError{code: status::StatusCode::BadRequest, error: format!("Unexpected file name {}, it must be rpm file", name)});
         }
 
-        file_path.to_str().unwrap().to_owned()
+        Ok(file_path.to_str().unwrap().to_owned())
+    }
+
+    // Maps the full request path onto the on-disk repo layout, e.g.
+    // `/myrepo/repodata/repomd.xml` -> `<repo_dir>/repodata/repomd.xml`.
+    fn resource_path(&self, repo_dir: &path::Path) -> path::PathBuf {
+        let mut resource_path = repo_dir.to_path_buf();
+
+        for part in &self.path_parts {
+            resource_path.push(part);
+        }
+
+        resource_path
     }
 }
 
 fn drop_non_string_comp(c: &path::Component) -> bool {
-    match *c {
-        path::Component::Normal(_) => {true}
-        _ => {false}
-    }
+    matches!(*c, path::Component::Normal(_))
 }
 
 fn convert_string_com(c: &path::Component) -> String {
@@ -88,87 +135,370 @@ fn parse_request(uri: &hyper::uri::RequestUri) -> Result<RepoRequest, HttpError>
             let path = path::Path::new(val);
 
             let components:Vec<String> = path.components().filter(drop_non_string_comp).map(|c| convert_string_com(&c)).collect();
-            match components.len() {
-                1 => {Ok(RepoRequest{repo_name: components[0].to_owned(), file_name: None})}
-                2 => {Ok(RepoRequest{repo_name: components[0].to_owned(), file_name: Some(components[1].to_owned())})}
-                _ => {Err(HttpError{code: status::StatusCode::BadRequest, error: "Invalid path specified".to_owned()})}
+
+            if components.is_empty() {
+                return Err(HttpError{code: status::StatusCode::BadRequest, error: "Invalid path specified".to_owned()});
             }
+
+            let repo_name = components[0].to_owned();
+            let path_parts = components[1..].to_vec();
+            Ok(RepoRequest{repo_name, path_parts})
         }
         _ => {
             Err(HttpError{code: status::StatusCode::BadRequest, error: "Invalid URI specified".to_owned()})
         }
-    } 
+    }
 }
 
-fn cache_arg(root: &String) -> String {
-    let cache_path = path::Path::new(root).join(path::Path::new("cache"));
-    let cache_str = cache_path.to_str().unwrap().to_owned();
+// Looks for a client-supplied SHA-256 digest, either as a plain hex header
+// or as the algorithm-prefixed value from the standard `Digest` header.
+fn expected_checksum(headers: &header::Headers) -> Option<String> {
+    if let Some(raw) = headers.get_raw("X-Checksum-Sha256") {
+        if let Some(value) = raw.first() {
+            return String::from_utf8(value.clone()).ok();
+        }
+    }
 
-    let mut cache_arg: String = "--cachedir=".to_owned();
+    if let Some(raw) = headers.get_raw("Digest") {
+        if let Some(value) = raw.first() {
+            if let Ok(text) = String::from_utf8(value.clone()) {
+                let mut parts = text.splitn(2, '=');
+                let algo = parts.next().unwrap_or("");
+                let digest = parts.next().unwrap_or("");
+
+                if algo.eq_ignore_ascii_case("sha-256") {
+                    // RFC 3230 carries the digest as base64; normalize it to
+                    // hex so it compares the same way as X-Checksum-Sha256.
+                    return base64::decode(digest).ok().map(hex::encode);
+                }
+            }
+        }
+    }
+
+    None
+}
 
-    cache_arg.push_str(&cache_str);
-    cache_arg
+fn content_type_for(path: &path::Path) -> hyper::mime::Mime {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("rpm") => "application/x-rpm".parse().unwrap(),
+        Some("xml") => "application/xml".parse().unwrap(),
+        Some("asc") => "application/pgp-signature".parse().unwrap(),
+        Some("key") => "application/pgp-keys".parse().unwrap(),
+        _ => "application/octet-stream".parse().unwrap()
+    }
+}
+
+enum GetResponse {
+    File(path::PathBuf, hyper::mime::Mime),
+    Status(JobStatus)
 }
 
 pub struct RestApiHandler{
     file_root: string::String,
-    refresh_lock: sync::Mutex<u8>
+    worker: Arc<RebuildWorker>,
+    auth: AuthConfig,
+    config: Option<Config>
 }
 
 impl RestApiHandler {
 
-    fn process_put_req(&self, mut req: server::Request)  {
-        let parsed_req = parse_request(&req.uri).unwrap();
-        parsed_req.ensure_repo_exists(&self.file_root);
+    // When a config is loaded, only repos it declares may be written to or
+    // read, and a repo's own `tokens` (if any) additionally restrict who
+    // may write to it on top of the globally accepted tokens.
+    fn check_repo_access(&self, repo_name: &str, headers: &header::Headers) -> Option<status::StatusCode> {
+        let config = match self.config {
+            Some(ref config) => config,
+            None => return None
+        };
+
+        let repo_config = match config.repo(repo_name) {
+            Some(repo_config) => repo_config,
+            None => return Some(status::StatusCode::NotFound)
+        };
+
+        if repo_config.tokens.is_empty() {
+            return None;
+        }
+
+        let allowed = self.auth.extract_token(headers)
+            .map(|token| auth::token_allowed(&token, &repo_config.tokens))
+            .unwrap_or(false);
 
-        let file_path = parsed_req.file_path(&self.file_root);
+        if allowed { None } else { Some(status::StatusCode::Forbidden) }
+    }
+
+    // Lets a credential listed only in a repo's own `tokens` satisfy the
+    // global mutating-method gate, so per-repo tokens work whether or not
+    // any `--token`/`--tokens-file` value is configured.
+    fn repo_token_allows(&self, repo_name: &str, headers: &header::Headers) -> bool {
+        let repo_config = match self.config.as_ref().and_then(|config| config.repo(repo_name)) {
+            Some(repo_config) => repo_config,
+            None => return false
+        };
+
+        self.auth.extract_token(headers)
+            .map(|token| auth::token_allowed(&token, &repo_config.tokens))
+            .unwrap_or(false)
+    }
 
-        let mut file = fs::File::create(&file_path).unwrap();
-        let copied = io::copy(&mut req, &mut file).unwrap();
-        debug!("Read {} bytes to file {}", copied, file_path);
+    fn repo_declared(&self, repo_name: &str) -> bool {
+        match self.config {
+            Some(ref config) => config.repo(repo_name).is_some(),
+            None => true
+        }
     }
 
-    fn process_post_req(&self, req: server::Request) -> status::StatusCode{
-        let parsed_req = parse_request(&req.uri).unwrap();
-        parsed_req.ensure_repo_exists(&self.file_root);
+    fn repo_dir(&self, repo_name: &str) -> path::PathBuf {
+        config::repo_dir(&self.file_root, self.config.as_ref(), repo_name)
+    }
 
-        let repo_path = parsed_req.repo_path(&self.file_root);
-        debug!("Rebuilding metadata for repo {}", repo_path);
+    fn repo_retain(&self, repo_name: &str) -> Option<usize> {
+        self.config.as_ref().and_then(|config| config.repo(repo_name)).and_then(|repo_config| repo_config.retain)
+    }
 
-        let cache_arg = cache_arg(&self.file_root);
+    fn process_put_req(&self, mut req: server::Request) -> status::StatusCode {
+        let parsed_req = match parse_request(&req.uri) {
+            Ok(parsed_req) => parsed_req,
+            Err(http_error) => return http_error.into_status()
+        };
 
-        let lock = self.refresh_lock.lock().unwrap();
-        let child_result = process::Command::new("createrepo").arg(&cache_arg).arg("--update").arg(&repo_path).spawn();
+        if let Some(code) = self.check_repo_access(&parsed_req.repo_name, &req.headers) {
+            return code;
+        }
 
-        match child_result {
-            Ok(mut child) => {
-                let exit_status = child.wait().unwrap();
-                if exit_status.success() {
-                    status::StatusCode::Ok
-                }else{
-                    error!("Failed to perform metadata refresh for repo {}, exit status {}", repo_path, exit_status);
-                    status::StatusCode::InternalServerError
+        let repo_dir = self.repo_dir(&parsed_req.repo_name);
+        parsed_req.ensure_repo_exists(&repo_dir);
+
+        let file_path = match parsed_req.file_path(&repo_dir) {
+            Ok(file_path) => file_path,
+            Err(http_error) => return http_error.into_status()
+        };
+
+        let status = match expected_checksum(&req.headers) {
+            Some(expected) => self.write_verified(&mut req, &file_path, &expected),
+            None => self.write_unverified(&mut req, &file_path)
+        };
+
+        if status == status::StatusCode::Ok {
+            if let Some(retain) = self.repo_retain(&parsed_req.repo_name) {
+                if retention::enforce(&repo_dir.join("rpms"), retain) {
+                    // Retention removed builds that repodata still
+                    // references, so catch it up the same way DELETE does.
+                    self.worker.enqueue(&parsed_req.repo_name);
                 }
             }
+        }
+
+        status
+    }
+
+    fn write_unverified(&self, req: &mut server::Request, file_path: &String) -> status::StatusCode {
+        let receive_result: io::Result<u64> = (|| {
+            let mut file = fs::File::create(file_path)?;
+            io::copy(req, &mut file)
+        })();
+
+        match receive_result {
+            Ok(copied) => {
+                debug!("Read {} bytes to file {}", copied, file_path);
+                status::StatusCode::Ok
+            }
             Err(error) => {
-                error!("Failed to spawn createrepo command, error {}", error);
-                status::StatusCode::InternalServerError
+                warn!("Failed to receive upload for {}, error {}", file_path, error);
+                fs::remove_file(file_path).ok();
+                status::StatusCode::BadRequest
+            }
+        }
+    }
+
+    // Streams the body through a SHA-256 hasher into `<file_path>.part`, only
+    // publishing it in place once the digest matches what the client claimed.
+    fn write_verified(&self, req: &mut server::Request, file_path: &String, expected: &str) -> status::StatusCode {
+        let temp_path = format!("{}.part", file_path);
+        let mut hasher = Sha256::default();
+
+        let receive_result: io::Result<()> = (|| {
+            let mut temp_file = fs::File::create(&temp_path)?;
+            let mut buffer = [0u8; 8192];
+
+            loop {
+                let read = req.read(&mut buffer)?;
+                if read == 0 {
+                    return Ok(());
+                }
+
+                hasher.input(&buffer[..read]);
+                temp_file.write_all(&buffer[..read])?;
             }
+        })();
+
+        if let Err(error) = receive_result {
+            warn!("Failed to receive upload for {}, error {}", file_path, error);
+            fs::remove_file(&temp_path).ok();
+            return status::StatusCode::BadRequest;
         }
+
+        let digest = hex::encode(hasher.result());
+
+        if !digest.eq_ignore_ascii_case(expected) {
+            warn!("Checksum mismatch writing {}: expected {}, got {}", file_path, expected, digest);
+            fs::remove_file(&temp_path).ok();
+            return status::StatusCode::BadRequest;
+        }
+
+        if let Err(error) = fs::rename(&temp_path, file_path) {
+            error!("Failed to publish {} from {}, error {}", file_path, temp_path, error);
+            fs::remove_file(&temp_path).ok();
+            return status::StatusCode::InternalServerError;
+        }
+
+        debug!("Verified checksum {} for {}", digest, file_path);
+        status::StatusCode::Ok
+    }
+
+    // Enqueues a rebuild with the background worker and returns immediately;
+    // the caller polls `GET /<repo>/_status` to learn when it lands.
+    fn process_post_req(&self, req: server::Request) -> (status::StatusCode, String) {
+        let parsed_req = match parse_request(&req.uri) {
+            Ok(parsed_req) => parsed_req,
+            Err(http_error) => return (http_error.into_status(), String::new())
+        };
+
+        if let Some(code) = self.check_repo_access(&parsed_req.repo_name, &req.headers) {
+            return (code, String::new());
+        }
+
+        let repo_dir = self.repo_dir(&parsed_req.repo_name);
+        parsed_req.ensure_repo_exists(&repo_dir);
+        self.worker.enqueue(&parsed_req.repo_name);
+
+        (status::StatusCode::Accepted, parsed_req.repo_name)
+    }
+
+    // Removes a published RPM and enqueues a rebuild so `repodata` catches
+    // up with its removal, using the same path resolution (and therefore
+    // the same traversal guards) as uploads.
+    fn process_delete_req(&self, req: server::Request) -> (status::StatusCode, String) {
+        let parsed_req = match parse_request(&req.uri) {
+            Ok(parsed_req) => parsed_req,
+            Err(http_error) => return (http_error.into_status(), String::new())
+        };
+
+        if let Some(code) = self.check_repo_access(&parsed_req.repo_name, &req.headers) {
+            return (code, String::new());
+        }
+
+        let repo_dir = self.repo_dir(&parsed_req.repo_name);
+
+        let file_path = match parsed_req.file_path(&repo_dir) {
+            Ok(file_path) => file_path,
+            Err(http_error) => return (http_error.into_status(), String::new())
+        };
+
+        if !path::Path::new(&file_path).is_file() {
+            return (status::StatusCode::NotFound, String::new());
+        }
+
+        match fs::remove_file(&file_path) {
+            Ok(()) => {
+                debug!("Deleted {}", file_path);
+                self.worker.enqueue(&parsed_req.repo_name);
+                (status::StatusCode::Ok, parsed_req.repo_name)
+            }
+            Err(error) => {
+                error!("Failed to delete {}, error {}", file_path, error);
+                (status::StatusCode::InternalServerError, String::new())
+            }
+        }
+    }
+
+    fn process_get_req(&self, req: &server::Request) -> Result<GetResponse, HttpError> {
+        let parsed_req = parse_request(&req.uri)?;
+
+        if !self.repo_declared(&parsed_req.repo_name) {
+            return Err(HttpError{code: status::StatusCode::NotFound, error: "Repository is not declared in config".to_owned()});
+        }
+
+        if parsed_req.path_parts.len() == 1 && parsed_req.path_parts[0] == "_status" {
+            return Ok(GetResponse::Status(self.worker.status(&parsed_req.repo_name)));
+        }
+
+        let repo_dir = self.repo_dir(&parsed_req.repo_name);
+        let resource_path = parsed_req.resource_path(&repo_dir);
+
+        if !resource_path.is_file() {
+            return Err(HttpError{code: status::StatusCode::NotFound, error: "Requested file does not exist".to_owned()});
+        }
+
+        let content_type = content_type_for(&resource_path);
+        Ok(GetResponse::File(resource_path, content_type))
+    }
+
+    fn stream_file(&self, file_path: path::PathBuf, content_type: hyper::mime::Mime, mut resp: server::Response) {
+        let mut file = match fs::File::open(&file_path) {
+            Ok(file) => file,
+            Err(error) => {
+                error!("Failed to open file {:?} for GET, error {}", file_path, error);
+                *resp.status_mut() = status::StatusCode::InternalServerError;
+                return;
+            }
+        };
+
+        let content_length = file.metadata().unwrap().len();
+
+        resp.headers_mut().set(header::ContentType(content_type));
+        resp.headers_mut().set(header::ContentLength(content_length));
+
+        let mut streaming = resp.start().unwrap();
+        io::copy(&mut file, &mut streaming).unwrap();
+        streaming.end().unwrap();
+    }
+
+    fn respond_with_status(&self, job_status: JobStatus, mut resp: server::Response) {
+        let body = job_status.as_str().as_bytes();
+        resp.headers_mut().set(header::ContentType::plaintext());
+        resp.send(body).unwrap();
     }
 }
 
 impl server::Handler for RestApiHandler {
     fn handle(&self, req: server::Request, mut resp: server::Response) {
+        let requires_auth = matches!(req.method, method::Method::Put | method::Method::Post | method::Method::Delete);
+
+        if requires_auth {
+            let repo_name = parse_request(&req.uri).ok().map(|parsed_req| parsed_req.repo_name);
+
+            let authorized = self.auth.authorize(&req.headers)
+                || repo_name.map(|repo_name| self.repo_token_allows(&repo_name, &req.headers)).unwrap_or(false);
+
+            if !authorized {
+                resp.headers_mut().set_raw("WWW-Authenticate", vec![b"Bearer".to_vec()]);
+                *resp.status_mut() = status::StatusCode::Unauthorized;
+                return;
+            }
+        }
+
         match req.method {
             method::Method::Put => {
-                self.process_put_req(req);
-                *resp.status_mut() = status::StatusCode::Ok;
+                *resp.status_mut() = self.process_put_req(req);
             }
             method::Method::Post => {
-                let status = self.process_post_req(req);
+                let (status, job_id) = self.process_post_req(req);
+                resp.headers_mut().set_raw("X-Job-Id", vec![job_id.into_bytes()]);
                 *resp.status_mut() = status;
             }
+            method::Method::Delete => {
+                let (status, job_id) = self.process_delete_req(req);
+                resp.headers_mut().set_raw("X-Job-Id", vec![job_id.into_bytes()]);
+                *resp.status_mut() = status;
+            }
+            method::Method::Get => {
+                match self.process_get_req(&req) {
+                    Ok(GetResponse::File(file_path, content_type)) => self.stream_file(file_path, content_type, resp),
+                    Ok(GetResponse::Status(job_status)) => self.respond_with_status(job_status, resp),
+                    Err(http_error) => *resp.status_mut() = http_error.into_status()
+                }
+            }
             _ => *resp.status_mut() = status::StatusCode::MethodNotAllowed
         }
     }
@@ -188,13 +518,127 @@ fn main() {
                                    .help("Sets root for the all managed RPM repositories")
                                    .required(true)
                                    .takes_value(true))
+                              .arg(clap::Arg::with_name("bind")
+                                   .long("bind")
+                                   .value_name("ADDR")
+                                   .help("Address and port to listen on")
+                                   .default_value("0.0.0.0:8080")
+                                   .takes_value(true))
+                              .arg(clap::Arg::with_name("tls_cert")
+                                   .long("tls-cert")
+                                   .value_name("FILE")
+                                   .help("PEM certificate chain to serve over TLS")
+                                   .requires("tls_key")
+                                   .takes_value(true))
+                              .arg(clap::Arg::with_name("tls_key")
+                                   .long("tls-key")
+                                   .value_name("FILE")
+                                   .help("PEM private key matching --tls-cert")
+                                   .requires("tls_cert")
+                                   .takes_value(true))
+                              .arg(clap::Arg::with_name("token")
+                                   .long("token")
+                                   .value_name("TOKEN")
+                                   .help("Accepted bearer token / basic auth password for PUT, POST and DELETE")
+                                   .multiple(true)
+                                   .takes_value(true))
+                              .arg(clap::Arg::with_name("tokens_file")
+                                   .long("tokens-file")
+                                   .value_name("FILE")
+                                   .help("File with one accepted token per line")
+                                   .takes_value(true))
+                              .arg(clap::Arg::with_name("trust_proxy_user_header")
+                                   .long("trust-proxy-user-header")
+                                   .value_name("HEADER")
+                                   .help("Trust the identity asserted in this header instead of validating tokens locally")
+                                   .conflicts_with_all(&["token", "tokens_file"])
+                                   .takes_value(true))
+                              .arg(clap::Arg::with_name("config")
+                                   .long("config")
+                                   .value_name("FILE")
+                                   .help("TOML or YAML file declaring the allowed repositories and their per-repo policy")
+                                   .takes_value(true))
                               .get_matches();
 
     let rpm_root = matches.value_of("rpm_root").unwrap();
+    let bind_addr = matches.value_of("bind").unwrap();
 
     info!("Got rpm_root: {}", rpm_root);
 
-    let handler = RestApiHandler{file_root: rpm_root.to_owned(), refresh_lock: sync::Mutex::new(0)};
+    let mut tokens: HashSet<String> = matches.values_of("token")
+        .map(|values| values.map(|v| v.to_owned()).collect())
+        .unwrap_or_default();
+
+    if let Some(tokens_file) = matches.value_of("tokens_file") {
+        tokens.extend(auth::load_tokens_file(tokens_file));
+    }
+
+    let trust_proxy_user_header = matches.value_of("trust_proxy_user_header").map(|h| h.to_owned());
+    let auth = AuthConfig::new(tokens, trust_proxy_user_header);
+
+    let repo_config = matches.value_of("config").map(config::load);
+
+    let worker = RebuildWorker::start(rpm_root.to_owned(), repo_config.clone(), REBUILD_WORKER_POOL_SIZE);
+    let handler = RestApiHandler{file_root: rpm_root.to_owned(), worker, auth, config: repo_config};
+
+    match (matches.value_of("tls_cert"), matches.value_of("tls_key")) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert_pem = fs::read(cert_path).unwrap_or_else(|error| {
+                panic!("Failed to read TLS certificate {}: {}", cert_path, error);
+            });
+            let key_pem = fs::read(key_path).unwrap_or_else(|error| {
+                panic!("Failed to read TLS key {}: {}", key_path, error);
+            });
+
+            let identity = Identity::from_pkcs8(&cert_pem, &key_pem).unwrap_or_else(|error| {
+                panic!("Failed to load TLS certificate {} / key {}: {}", cert_path, key_path, error);
+            });
 
-    server::Server::http("0.0.0.0:8080").unwrap().handle(handler).unwrap();
+            let acceptor = TlsAcceptor::builder(identity).build().unwrap_or_else(|error| {
+                panic!("Failed to build TLS acceptor for certificate {}: {}", cert_path, error);
+            });
+
+            let ssl = NativeTlsServer::from(acceptor);
+
+            info!("Listening on {} (TLS)", bind_addr);
+            server::Server::https(bind_addr, ssl).unwrap().handle(handler).unwrap();
+        }
+        _ => {
+            info!("Listening on {}", bind_addr);
+            server::Server::http(bind_addr).unwrap().handle(handler).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod expected_checksum_tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_bespoke_hex_header() {
+        let mut headers = header::Headers::new();
+        headers.set_raw("X-Checksum-Sha256", vec![b"deadbeef".to_vec()]);
+        assert_eq!(expected_checksum(&headers), Some("deadbeef".to_owned()));
+    }
+
+    #[test]
+    fn decodes_base64_from_the_standard_digest_header() {
+        let mut headers = header::Headers::new();
+        // base64("\xde\xad\xbe\xef") == "3q2+7w=="
+        headers.set_raw("Digest", vec![b"sha-256=3q2+7w==".to_vec()]);
+        assert_eq!(expected_checksum(&headers), Some("deadbeef".to_owned()));
+    }
+
+    #[test]
+    fn ignores_digest_headers_for_other_algorithms() {
+        let mut headers = header::Headers::new();
+        headers.set_raw("Digest", vec![b"md5=3q2+7w==".to_vec()]);
+        assert_eq!(expected_checksum(&headers), None);
+    }
+
+    #[test]
+    fn returns_none_without_either_header() {
+        let headers = header::Headers::new();
+        assert_eq!(expected_checksum(&headers), None);
+    }
 }
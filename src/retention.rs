@@ -0,0 +1,99 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::path;
+use std::time;
+
+// Keeps only the `retain` newest builds of each name-arch package, deleting
+// older ones so repos fed by frequent CI uploads don't fill the disk.
+// Returns whether anything was actually deleted, so callers know whether
+// `repodata` needs a rebuild to catch up.
+pub fn enforce(rpms_dir: &path::Path, retain: usize) -> bool {
+    let entries = match fs::read_dir(rpms_dir) {
+        Ok(entries) => entries,
+        Err(error) => {
+            error!("Failed to list {:?} for retention, error {}", rpms_dir, error);
+            return false;
+        }
+    };
+
+    let mut builds_by_package: HashMap<String, Vec<(path::PathBuf, time::SystemTime)>> = HashMap::new();
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+
+        let file_name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(file_name) => file_name,
+            None => continue
+        };
+
+        let package_key = match package_key(file_name) {
+            Some(package_key) => package_key,
+            None => continue
+        };
+
+        let modified = entry.metadata().and_then(|metadata| metadata.modified()).unwrap_or(time::UNIX_EPOCH);
+        builds_by_package.entry(package_key).or_default().push((path, modified));
+    }
+
+    let mut deleted_any = false;
+
+    for (package_key, mut builds) in builds_by_package {
+        if builds.len() <= retain {
+            continue;
+        }
+
+        builds.sort_by_key(|build| cmp::Reverse(build.1));
+
+        for (path, _) in builds.drain(retain..) {
+            debug!("Retention: removing old build {:?} of {}", path, package_key);
+            fs::remove_file(&path).ok();
+            deleted_any = true;
+        }
+    }
+
+    deleted_any
+}
+
+// Approximates NEVRA parsing: `<name>-<version>-<release>.<arch>.rpm` ->
+// `<name>.<arch>`, the identity a retention policy groups builds by.
+fn package_key(file_name: &str) -> Option<String> {
+    let stem = file_name.trim_end_matches(".rpm");
+
+    if stem == file_name {
+        return None;
+    }
+
+    let dot_pos = stem.rfind('.')?;
+    let arch = &stem[dot_pos + 1..];
+    let name_version_release = &stem[..dot_pos];
+
+    let parts: Vec<&str> = name_version_release.rsplitn(3, '-').collect();
+
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let name = parts[2];
+    Some(format!("{}.{}", name, arch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_key_groups_by_name_and_arch() {
+        assert_eq!(package_key("myapp-1.2.3-1.x86_64.rpm"), Some("myapp.x86_64".to_owned()));
+    }
+
+    #[test]
+    fn package_key_ignores_non_rpm_files() {
+        assert_eq!(package_key("repomd.xml"), None);
+    }
+
+    #[test]
+    fn package_key_ignores_names_without_enough_dashes() {
+        assert_eq!(package_key("norelease.x86_64.rpm"), None);
+    }
+}
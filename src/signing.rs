@@ -0,0 +1,63 @@
+use std::path;
+use std::process;
+
+// Produces a detached, armored GPG signature for `repodata/repomd.xml` so
+// dnf clients with `repo_gpgcheck` enabled can trust the metadata, and
+// exports the matching public key alongside it for easy retrieval.
+pub fn sign_repomd(repo_dir: &path::Path, signing_key: &str, passphrase: Option<&String>) -> bool {
+    let repodata_dir = repo_dir.join("repodata");
+    let repomd_path = repodata_dir.join("repomd.xml");
+
+    if !repomd_path.is_file() {
+        error!("Cannot sign missing {:?}", repomd_path);
+        return false;
+    }
+
+    let asc_path = repodata_dir.join("repomd.xml.asc");
+
+    if !run_gpg(signing_key, passphrase, |command| {
+        command.arg("--detach-sign").arg("--armor")
+            .arg("--output").arg(&asc_path)
+            .arg(&repomd_path);
+    }) {
+        return false;
+    }
+
+    // Exporting the public key is a convenience for clients fetching it
+    // alongside the repo, not a precondition for trusting the signature
+    // already written above, so a failure here is logged but not fatal.
+    let key_path = repodata_dir.join("repomd.xml.key");
+
+    if !run_gpg(signing_key, passphrase, |command| {
+        command.arg("--export").arg("--armor")
+            .arg("--output").arg(&key_path)
+            .arg(signing_key);
+    }) {
+        error!("Signed repomd.xml for key {} but failed to export its public key", signing_key);
+    }
+
+    true
+}
+
+fn run_gpg<F: FnOnce(&mut process::Command)>(signing_key: &str, passphrase: Option<&String>, configure: F) -> bool {
+    let mut command = process::Command::new("gpg");
+    command.arg("--batch").arg("--yes").arg("--local-user").arg(signing_key);
+
+    if let Some(passphrase) = passphrase {
+        command.arg("--pinentry-mode").arg("loopback").arg("--passphrase").arg(passphrase);
+    }
+
+    configure(&mut command);
+
+    match command.status() {
+        Ok(exit_status) if exit_status.success() => true,
+        Ok(exit_status) => {
+            error!("gpg exited with {} signing with key {}", exit_status, signing_key);
+            false
+        }
+        Err(error) => {
+            error!("Failed to spawn gpg with key {}, error {}", signing_key, error);
+            false
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use config;
+use config::Config;
+use signing;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path;
+use std::process;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Idle,
+    Queued,
+    Running,
+    Succeeded,
+    Failed
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            JobStatus::Idle => "idle",
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed"
+        }
+    }
+}
+
+struct WorkerState {
+    pending: HashSet<String>,
+    running: HashSet<String>,
+    last_status: HashMap<String, JobStatus>
+}
+
+// Coalesces `createrepo` runs so a burst of uploads followed by N POSTs
+// results in at most one rebuild in flight per repo plus one queued
+// follow-up, instead of N serialized full-repo scans.
+pub struct RebuildWorker {
+    file_root: String,
+    config: Option<Config>,
+    state: Mutex<WorkerState>,
+    condvar: Condvar
+}
+
+impl RebuildWorker {
+
+    pub fn start(file_root: String, config: Option<Config>, pool_size: usize) -> Arc<RebuildWorker> {
+        let worker = Arc::new(RebuildWorker {
+            file_root,
+            config,
+            state: Mutex::new(WorkerState {
+                pending: HashSet::new(),
+                running: HashSet::new(),
+                last_status: HashMap::new()
+            }),
+            condvar: Condvar::new()
+        });
+
+        for _ in 0..pool_size {
+            let worker = worker.clone();
+            thread::spawn(move || worker.run());
+        }
+
+        worker
+    }
+
+    pub fn enqueue(&self, repo_name: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.pending.insert(repo_name.to_owned());
+        self.condvar.notify_all();
+    }
+
+    pub fn status(&self, repo_name: &str) -> JobStatus {
+        let state = self.state.lock().unwrap();
+
+        if state.running.contains(repo_name) {
+            JobStatus::Running
+        } else if state.pending.contains(repo_name) {
+            JobStatus::Queued
+        } else {
+            state.last_status.get(repo_name).cloned().unwrap_or(JobStatus::Idle)
+        }
+    }
+
+    fn run(&self) {
+        loop {
+            let repo_name = self.take_next_pending();
+            let repo_dir = config::repo_dir(&self.file_root, self.config.as_ref(), &repo_name);
+            let repo_config = self.config.as_ref().and_then(|config| config.repo(&repo_name));
+            let result = rebuild(&repo_dir, &repo_name, repo_config);
+
+            let mut state = self.state.lock().unwrap();
+            state.running.remove(&repo_name);
+            state.last_status.insert(repo_name, result);
+
+            // A repo that was re-enqueued while its rebuild was running is
+            // now eligible to be picked up, so wake any worker waiting on it.
+            self.condvar.notify_all();
+        }
+    }
+
+    // Only hands out a repo that isn't already `running`, so a repo queued
+    // again mid-rebuild waits for the in-flight run to finish instead of
+    // letting two workers `createrepo --update` the same directory at once.
+    fn take_next_pending(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            let next_pending = select_next_pending(&state.pending, &state.running).cloned();
+
+            if let Some(repo_name) = next_pending {
+                state.pending.remove(&repo_name);
+                state.running.insert(repo_name.clone());
+                return repo_name;
+            }
+
+            state = self.condvar.wait(state).unwrap();
+        }
+    }
+}
+
+// Picks a pending repo that isn't already running, so a repo re-enqueued
+// mid-rebuild waits for the in-flight run instead of starting a second one.
+fn select_next_pending<'a>(pending: &'a HashSet<String>, running: &HashSet<String>) -> Option<&'a String> {
+    pending.iter().find(|repo_name| !running.contains(*repo_name))
+}
+
+fn cache_arg(root: &str) -> String {
+    let cache_path = path::Path::new(root).join(path::Path::new("cache"));
+    let cache_str = cache_path.to_str().unwrap().to_owned();
+
+    let mut cache_arg: String = "--cachedir=".to_owned();
+
+    cache_arg.push_str(&cache_str);
+    cache_arg
+}
+
+fn rebuild(repo_dir: &path::Path, repo_name: &str, repo_config: Option<&config::RepoConfig>) -> JobStatus {
+    let repo_path = repo_dir.to_str().unwrap().to_owned();
+    let cache_arg = cache_arg(&repo_path);
+
+    debug!("Rebuilding metadata for repo {} at {}", repo_name, repo_path);
+
+    let child_result = process::Command::new("createrepo").arg(&cache_arg).arg("--update").arg(&repo_path).spawn();
+
+    let createrepo_ok = match child_result {
+        Ok(mut child) => {
+            let exit_status = child.wait().unwrap();
+            if exit_status.success() {
+                true
+            } else {
+                error!("Failed to perform metadata refresh for repo {} at {}, exit status {}", repo_name, repo_path, exit_status);
+                false
+            }
+        }
+        Err(error) => {
+            error!("Failed to spawn createrepo command for repo {}, error {}", repo_name, error);
+            false
+        }
+    };
+
+    if !createrepo_ok {
+        return JobStatus::Failed;
+    }
+
+    match repo_config.and_then(|repo_config| repo_config.signing_key.as_ref()) {
+        Some(signing_key) => {
+            let passphrase = repo_config.and_then(|repo_config| repo_config.signing_passphrase.as_ref());
+
+            if signing::sign_repomd(repo_dir, signing_key, passphrase) {
+                JobStatus::Succeeded
+            } else {
+                error!("Metadata for repo {} rebuilt but signing with key {} failed", repo_name, signing_key);
+                JobStatus::Failed
+            }
+        }
+        None => JobStatus::Succeeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_next_pending_skips_repos_already_running() {
+        let mut pending = HashSet::new();
+        pending.insert("a".to_owned());
+        pending.insert("b".to_owned());
+
+        let mut running = HashSet::new();
+        running.insert("a".to_owned());
+
+        assert_eq!(select_next_pending(&pending, &running), Some(&"b".to_owned()));
+    }
+
+    #[test]
+    fn select_next_pending_waits_when_everything_is_running() {
+        let mut pending = HashSet::new();
+        pending.insert("a".to_owned());
+
+        let mut running = HashSet::new();
+        running.insert("a".to_owned());
+
+        assert_eq!(select_next_pending(&pending, &running), None);
+    }
+}